@@ -0,0 +1,209 @@
+//! Strftime-like format descriptors, for vendor epoch dialects that don't
+//! follow the fixed-column RINEX layout.
+
+use crate::epoch::{build_epoch, to_gregorian_in_timescale, ParsingError};
+use hifitime::{Epoch, TimeScale};
+
+/*
+ * A single compiled field of a format descriptor, produced by `compile()`
+ * from a strftime-like pattern: %Y %m %d %H %M %S %f, anything else is a
+ * literal separator.
+ */
+#[derive(Debug, Clone, PartialEq)]
+enum FormatItem {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Fraction,
+    Literal(String),
+}
+
+/*
+ * Compiles a strftime-like pattern into an ordered list of `FormatItem`s.
+ */
+fn compile(fmt: &str) -> Vec<FormatItem> {
+    let mut items = Vec::new();
+    let mut literal = String::new();
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            let token = match chars.peek() {
+                Some('Y') => Some(FormatItem::Year),
+                Some('m') => Some(FormatItem::Month),
+                Some('d') => Some(FormatItem::Day),
+                Some('H') => Some(FormatItem::Hour),
+                Some('M') => Some(FormatItem::Minute),
+                Some('S') => Some(FormatItem::Second),
+                Some('f') => Some(FormatItem::Fraction),
+                _ => None,
+            };
+            if let Some(item) = token {
+                if !literal.is_empty() {
+                    items.push(FormatItem::Literal(std::mem::take(&mut literal)));
+                }
+                items.push(item);
+                chars.next();
+                continue;
+            }
+        }
+        literal.push(c);
+    }
+
+    if !literal.is_empty() {
+        items.push(FormatItem::Literal(literal));
+    }
+
+    items
+}
+
+/*
+ * Splits off up to `max` leading ASCII digits from `s`.
+ */
+fn take_digits(s: &str, max: usize) -> (&str, &str) {
+    let mut end = 0;
+    for (count, (i, c)) in s.char_indices().enumerate() {
+        if count >= max || !c.is_ascii_digit() {
+            break;
+        }
+        end = i + c.len_utf8();
+    }
+    s.split_at(end)
+}
+
+/// Parses `content` into an `Epoch` within the given `TimeScale`, following
+/// the field order described by the strftime-like `fmt` descriptor.
+pub fn parse_with_format(content: &str, ts: TimeScale, fmt: &str) -> Result<Epoch, ParsingError> {
+    let items = compile(fmt);
+    let mut rem = content.trim();
+
+    let mut y = 0_i32;
+    let mut m = 1_u8;
+    let mut d = 1_u8;
+    let mut hh = 0_u8;
+    let mut mm = 0_u8;
+    let mut ss = 0_u8;
+    let mut ns = 0_u32;
+
+    for item in &items {
+        match item {
+            FormatItem::Literal(lit) => {
+                rem = rem.strip_prefix(lit.as_str()).ok_or(ParsingError::FormatError)?;
+            },
+            FormatItem::Year => {
+                let (digits, tail) = take_digits(rem, 4);
+                y = digits
+                    .parse::<i32>()
+                    .map_err(|_| ParsingError::YearField(digits.to_string()))?;
+                rem = tail;
+            },
+            FormatItem::Month => {
+                let (digits, tail) = take_digits(rem, 2);
+                m = digits
+                    .parse::<u8>()
+                    .map_err(|_| ParsingError::MonthField(digits.to_string()))?;
+                rem = tail;
+            },
+            FormatItem::Day => {
+                let (digits, tail) = take_digits(rem, 2);
+                d = digits
+                    .parse::<u8>()
+                    .map_err(|_| ParsingError::DayField(digits.to_string()))?;
+                rem = tail;
+            },
+            FormatItem::Hour => {
+                let (digits, tail) = take_digits(rem, 2);
+                hh = digits
+                    .parse::<u8>()
+                    .map_err(|_| ParsingError::HoursField(digits.to_string()))?;
+                rem = tail;
+            },
+            FormatItem::Minute => {
+                let (digits, tail) = take_digits(rem, 2);
+                mm = digits
+                    .parse::<u8>()
+                    .map_err(|_| ParsingError::MinutesField(digits.to_string()))?;
+                rem = tail;
+            },
+            FormatItem::Second => {
+                let (digits, tail) = take_digits(rem, 2);
+                ss = digits
+                    .parse::<u8>()
+                    .map_err(|_| ParsingError::SecondsField(digits.to_string()))?;
+                rem = tail;
+            },
+            FormatItem::Fraction => {
+                let (digits, tail) = take_digits(rem, 9);
+                if !digits.is_empty() {
+                    let scale = 10_u32.pow(9 - digits.len() as u32);
+                    ns = digits
+                        .parse::<u32>()
+                        .map_err(|_| ParsingError::NanosecondsField(digits.to_string()))?
+                        * scale;
+                }
+                rem = tail;
+            },
+        }
+    }
+
+    build_epoch(y, m, d, hh, mm, ss, ns, ts)
+}
+
+/// Formats `epoch`, expressed in its own `TimeScale`, following the field
+/// order described by the strftime-like `fmt` descriptor.
+pub fn format_with(epoch: Epoch, fmt: &str) -> String {
+    let (y, m, d, hh, mm, ss, ns) = to_gregorian_in_timescale(epoch);
+    let items = compile(fmt);
+    let mut out = String::new();
+
+    for item in &items {
+        match item {
+            FormatItem::Literal(lit) => out.push_str(lit),
+            FormatItem::Year => out.push_str(&format!("{:04}", y)),
+            FormatItem::Month => out.push_str(&format!("{:02}", m)),
+            FormatItem::Day => out.push_str(&format!("{:02}", d)),
+            FormatItem::Hour => out.push_str(&format!("{:02}", hh)),
+            FormatItem::Minute => out.push_str(&format!("{:02}", mm)),
+            FormatItem::Second => out.push_str(&format!("{:02}", ss)),
+            FormatItem::Fraction => out.push_str(&format!("{:09}", ns)),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_iso_like_dialect() {
+        let e = parse_with_format(
+            "2022-03-04T00:52:30.500000000",
+            TimeScale::UTC,
+            "%Y-%m-%dT%H:%M:%S.%f",
+        );
+        assert!(e.is_ok());
+        let (y, m, d, hh, mm, ss, ns) = to_gregorian_in_timescale(e.unwrap());
+        assert_eq!((y, m, d, hh, mm, ss, ns), (2022, 3, 4, 0, 52, 30, 500_000_000));
+    }
+
+    #[test]
+    fn format_with_roundtrip() {
+        let e = parse_with_format("2022 03 04 00 52 30", TimeScale::UTC, "%Y %m %d %H %M %S")
+            .unwrap();
+        assert_eq!(
+            format_with(e, "%Y-%m-%dT%H:%M:%S"),
+            "2022-03-04T00:52:30"
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_literal() {
+        let e = parse_with_format("2022/03/04", TimeScale::UTC, "%Y-%m-%d");
+        assert!(e.is_err());
+    }
+}
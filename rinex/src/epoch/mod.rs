@@ -6,6 +6,12 @@ use thiserror::Error;
 pub mod flag;
 pub use flag::EpochFlag;
 
+pub mod format;
+pub use format::{format_with, parse_with_format};
+
+pub mod rfc3339;
+pub use rfc3339::{parse_rfc3339, to_rfc3339};
+
 #[derive(Error, Debug)]
 pub enum ParsingError {
     #[error("failed to parse epoch flag")]
@@ -30,6 +36,21 @@ pub enum ParsingError {
     SecondsField(String),
     #[error("failed to parse nanos from \"{0}\"")]
     NanosecondsField(String),
+    #[error("month out of range: {value} (expected 1..=12)")]
+    MonthOutOfRange { value: u8 },
+    #[error("day out of range: {value} (expected 1..={max} for {year:04}-{month:02})")]
+    DayOutOfRange {
+        value: u8,
+        max: u8,
+        year: i32,
+        month: u8,
+    },
+    #[error("hour out of range: {value} (expected 0..=23)")]
+    HourOutOfRange { value: u8 },
+    #[error("minute out of range: {value} (expected 0..=59)")]
+    MinuteOutOfRange { value: u8 },
+    #[error("second out of range: {value} (expected 0..=60, to allow leap seconds)")]
+    SecondOutOfRange { value: u8 },
 }
 
 /*
@@ -39,17 +60,118 @@ pub(crate) fn now() -> Epoch {
     Epoch::now().unwrap_or(Epoch::from_gregorian_utc_at_midnight(2000, 1, 1))
 }
 
+/*
+ * Calendar date of `t=0` for each continuous, leap-second-free GNSS
+ * timescale: the day at which that timescale is defined to start counting.
+ * Used only as the base date for the pure calendar (no-leap-second) day
+ * arithmetic in `to_gregorian_in_timescale()`; the elapsed duration since
+ * that origin is obtained from hifitime itself, not by subtracting Epochs
+ * of mismatched timescales.
+ */
+fn timescale_origin_date(ts: TimeScale) -> (i32, u8, u8) {
+    match ts {
+        TimeScale::GPST => (1980, 1, 6),
+        TimeScale::GST => (1999, 8, 22),
+        TimeScale::BDT => (2006, 1, 1),
+        _ => (1900, 1, 1),
+    }
+}
+
+/*
+ * Proleptic Gregorian calendar arithmetic (Howard Hinnant's days_from_civil
+ * / civil_from_days), used to add a day count to a calendar date without
+ * involving any leap-second table. This is what lets us decompose a
+ * continuous timescale (GPST, GST, BDT) directly, instead of round-tripping
+ * through a leap-second aware UTC conversion.
+ */
+fn days_from_civil(y: i32, m: u8, d: u8) -> i64 {
+    let y = y as i64 - i64::from(m <= 2);
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = (y - era * 400) as i64; // [0, 399]
+    let mp = (i64::from(m) + 9) % 12; // [0, 11] : Mar=0, ..., Feb=11
+    let doy = (153 * mp + 2) / 5 + i64::from(d) - 1; // [0, 365]
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy; // [0, 146096]
+    era * 146097 + doe - 719468
+}
+
+fn is_leap_year(y: i32) -> bool {
+    (y % 4 == 0 && y % 100 != 0) || y % 400 == 0
+}
+
+/*
+ * Number of days in the given (year, month), month/leap-year aware.
+ * `month` is assumed already range-checked to 1..=12.
+ */
+fn days_in_month(y: i32, m: u8) -> u8 {
+    match m {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 => {
+            if is_leap_year(y) {
+                29
+            } else {
+                28
+            }
+        },
+        _ => 0,
+    }
+}
+
+fn civil_from_days(z: i64) -> (i32, u8, u8) {
+    let z = z + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097; // [0, 146096]
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365; // [0, 399]
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // [0, 365]
+    let mp = (5 * doy + 2) / 153; // [0, 11]
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u8; // [1, 31]
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u8; // [1, 12]
+    let y = if m <= 2 { y + 1 } else { y };
+    (y as i32, m, d)
+}
+
+/*
+ * Decomposes an epoch into Gregorian calendar fields expressed in its own
+ * `time_scale`, without applying any UTC leap-second correction to
+ * non-UTC (continuous) timescales.
+ */
+pub(crate) fn to_gregorian_in_timescale(epoch: Epoch) -> (i32, u8, u8, u8, u8, u8, u32) {
+    if epoch.time_scale == TimeScale::UTC {
+        return epoch.to_gregorian_utc();
+    }
+
+    // Ask hifitime directly for the duration since the timescale's own
+    // reference epoch, rather than subtracting a manually constructed
+    // "origin" `Epoch`: `epoch.time_scale` already matches the argument
+    // here, so this is an identity read of `epoch`'s own duration, not a
+    // conversion; it's deliberately explicit so this code never depends on
+    // `Sub for Epoch`'s implicit timescale-conversion behavior.
+    let elapsed = epoch.to_duration_in_time_scale(epoch.time_scale);
+    let total_ns = elapsed.total_nanoseconds() as i64;
+
+    const NS_PER_DAY: i64 = 86_400_000_000_000;
+    let days = total_ns.div_euclid(NS_PER_DAY);
+    let mut ns_of_day = total_ns.rem_euclid(NS_PER_DAY);
+
+    let (origin_y, origin_m, origin_d) = timescale_origin_date(epoch.time_scale);
+    let (y, m, d) = civil_from_days(days_from_civil(origin_y, origin_m, origin_d) + days);
+
+    let hh = (ns_of_day / 3_600_000_000_000) as u8;
+    ns_of_day %= 3_600_000_000_000;
+    let mm = (ns_of_day / 60_000_000_000) as u8;
+    ns_of_day %= 60_000_000_000;
+    let ss = (ns_of_day / 1_000_000_000) as u8;
+    let ns = (ns_of_day % 1_000_000_000) as u32;
+
+    (y, m, d, hh, mm, ss, ns)
+}
+
 /*
  * Formats given epoch to string, matching standard specifications
  */
 pub(crate) fn format(epoch: Epoch, flag: Option<EpochFlag>, t: Type, revision: u8) -> String {
-    // Hifitime V3 does not have a gregorian decomposition method
-    let (y, m, d, hh, mm, ss, nanos) = match epoch.time_scale {
-        TimeScale::GPST => (epoch + Duration::from_seconds(37.0)).to_gregorian_utc(),
-        TimeScale::GST => (epoch + Duration::from_seconds(19.0)).to_gregorian_utc(),
-        TimeScale::BDT => (epoch + Duration::from_seconds(19.0)).to_gregorian_utc(),
-        _ => epoch.to_gregorian_utc(),
-    };
+    let (y, m, d, hh, mm, ss, nanos) = to_gregorian_in_timescale(epoch);
 
     match t {
         Type::ObservationData => {
@@ -217,43 +339,114 @@ pub(crate) fn parse_in_timescale(
     //println!("content \"{}\"", content); // DEBUG
     //println!("Y {} M {} D {} HH {} MM {} SS {} NS {} FLAG {}", y, m, d, hh, mm, ss, ns, flag); // DEBUG
 
+    let epoch = build_epoch(y, m, d, hh, mm, ss, ns, ts)?;
+    Ok((epoch, flag))
+}
+
+pub(crate) fn parse_utc(s: &str) -> Result<(Epoch, EpochFlag), ParsingError> {
+    parse_in_timescale(s, TimeScale::UTC)
+}
+
+/// Truncates `epoch` down to the nearest multiple of `interval`, analogous
+/// to chrono's `DurationRound::duration_trunc()`. Delegates to hifitime's
+/// own `Epoch::floor()`, which operates on the epoch's native `TimeScale`
+/// (the result is never silently shifted into UTC). A zero `interval` is a
+/// no-op: `epoch` is returned unchanged.
+pub fn truncate_to(epoch: Epoch, interval: Duration) -> Epoch {
+    if interval.total_nanoseconds() == 0 {
+        return epoch;
+    }
+
+    epoch.floor(interval)
+}
+
+/// Rounds `epoch` to the nearest multiple of `interval`, ties rounding up.
+/// Delegates to hifitime's own `Epoch::round()`, which operates on the
+/// epoch's native `TimeScale` (the result is never silently shifted into
+/// UTC). A zero `interval` is a no-op: `epoch` is returned unchanged.
+pub fn round_to(epoch: Epoch, interval: Duration) -> Epoch {
+    if interval.total_nanoseconds() == 0 {
+        return epoch;
+    }
+
+    epoch.round(interval)
+}
+
+/*
+ * Builds an `Epoch` from individually parsed Gregorian fields, in the
+ * given `TimeScale`. Shared by `parse_in_timescale()` and the
+ * format-descriptor driven `format::parse_with_format()`.
+ */
+pub(crate) fn build_epoch(
+    y: i32,
+    m: u8,
+    d: u8,
+    hh: u8,
+    mm: u8,
+    ss: u8,
+    ns: u32,
+    ts: TimeScale,
+) -> Result<Epoch, ParsingError> {
+    // in case provided content is totally invalid,
+    // we end up here with y == 0. And Epoch::from_gregorian / Epoch::from_str may panic
+    if y == 0 {
+        return Err(ParsingError::FormatError);
+    }
+
+    // `Epoch::from_gregorian_utc` / `Epoch::from_str` may panic on out of
+    // range components: validate each field first, so a corrupt line turns
+    // into a recoverable, skippable error instead of killing the process.
+    if !(1..=12).contains(&m) {
+        return Err(ParsingError::MonthOutOfRange { value: m });
+    }
+
+    let max_day = days_in_month(y, m);
+    if d < 1 || d > max_day {
+        return Err(ParsingError::DayOutOfRange {
+            value: d,
+            max: max_day,
+            year: y,
+            month: m,
+        });
+    }
+
+    if hh > 23 {
+        return Err(ParsingError::HourOutOfRange { value: hh });
+    }
+
+    if mm > 59 {
+        return Err(ParsingError::MinuteOutOfRange { value: mm });
+    }
+
+    if ss > 60 {
+        // 60 is allowed: leap second
+        return Err(ParsingError::SecondOutOfRange { value: ss });
+    }
+
     match ts {
         TimeScale::UTC => {
-            // in case provided content is totally invalid,
-            // we end up here with. And Epoch::from_gregorian will panic
-            if y == 0 {
-                return Err(ParsingError::FormatError);
-            }
-
-            let epoch = Epoch::from_gregorian_utc(y, m, d, hh, mm, ss, ns);
-            Ok((epoch, flag))
+            // `from_gregorian_utc()` panics on a date it considers invalid
+            // (e.g. ss == 60 outside an actual leap-second instant): our own
+            // guard above only checks the 0..=60 range, not hifitime's
+            // stricter historical leap-second table, so go through the
+            // fallible constructor and report any mismatch as a regular
+            // parsing error instead of crashing on it.
+            Ok(Epoch::maybe_from_gregorian_utc(y, m, d, hh, mm, ss, ns)?)
         },
         _ => {
-            // in case provided content is totally invalid,
-            // we end up here with. And Epoch::from_string may panic
-            if y == 0 {
-                return Err(ParsingError::FormatError);
-            }
+            // `ns` is already a nanosecond count: the `.{:09}` field below
+            // expects one, not the decisecond digit `ns / 100_000_000` used
+            // to produce here previously (which silently turned e.g.
+            // 500_000_000ns into a re-parsed 5ns).
             let epoch = Epoch::from_str(&format!(
                 "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09} {}",
-                y,
-                m,
-                d,
-                hh,
-                mm,
-                ss,
-                ns / 100_000_000,
-                ts
+                y, m, d, hh, mm, ss, ns, ts
             ))?;
-            Ok((epoch, flag))
+            Ok(epoch)
         },
     }
 }
 
-pub(crate) fn parse_utc(s: &str) -> Result<(Epoch, EpochFlag), ParsingError> {
-    parse_in_timescale(s, TimeScale::UTC)
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -580,4 +773,124 @@ mod test {
         assert_eq!(ns, 0);
         //assert_eq!(format!("{}", e), "2022 03 04 00 02 30.0000000  0");
     }
+    #[test]
+    fn gpst_format_predates_current_leap_second_count() {
+        // GPST epoch predating the 2017 leap second: the native decomposition
+        // must not be shifted by today's (37s) GPST-UTC offset.
+        let epoch = Epoch::from_str("2015-01-01T00:00:00 GPST").unwrap();
+        assert_eq!(
+            format(epoch, None, Type::NavigationData, 3),
+            "2015 01 01 00 00 00"
+        );
+    }
+    #[test]
+    fn gpst_format_at_its_own_origin() {
+        let epoch = Epoch::from_str("1980-01-06T00:00:30 GPST").unwrap();
+        assert_eq!(
+            format(epoch, None, Type::NavigationData, 3),
+            "1980 01 06 00 00 30"
+        );
+    }
+    #[test]
+    fn epoch_truncate_to_30s() {
+        let e = Epoch::from_str("2022-01-09T00:00:43 GPST").unwrap();
+        let trunc = truncate_to(e, Duration::from_seconds(30.0));
+        assert_eq!(
+            format(trunc, None, Type::NavigationData, 3),
+            "2022 01 09 00 00 30"
+        );
+        assert_eq!(trunc.time_scale, TimeScale::GPST);
+    }
+    #[test]
+    fn epoch_round_to_30s() {
+        let e = Epoch::from_str("2022-01-09T00:00:46 GPST").unwrap();
+        let rounded = round_to(e, Duration::from_seconds(30.0));
+        assert_eq!(
+            format(rounded, None, Type::NavigationData, 3),
+            "2022 01 09 00 01 00"
+        );
+        assert_eq!(rounded.time_scale, TimeScale::GPST);
+    }
+    #[test]
+    fn epoch_round_to_ties_go_up() {
+        let e = Epoch::from_str("2022-01-09T00:00:15 GPST").unwrap();
+        let rounded = round_to(e, Duration::from_seconds(30.0));
+        assert_eq!(
+            format(rounded, None, Type::NavigationData, 3),
+            "2022 01 09 00 00 30"
+        );
+    }
+    #[test]
+    fn epoch_round_truncate_zero_interval_is_noop() {
+        let e = Epoch::from_str("2022-01-09T00:00:46 GPST").unwrap();
+        assert_eq!(round_to(e, Duration::from_seconds(0.0)), e);
+        assert_eq!(truncate_to(e, Duration::from_seconds(0.0)), e);
+    }
+    #[test]
+    fn epoch_truncate_to_30s_utc() {
+        let e = Epoch::from_str("2022-01-09T00:00:43 UTC").unwrap();
+        let trunc = truncate_to(e, Duration::from_seconds(30.0));
+        assert_eq!(
+            format(trunc, None, Type::NavigationData, 3),
+            "2022 01 09 00 00 30"
+        );
+    }
+    #[test]
+    fn epoch_parse_rejects_invalid_month() {
+        let e = parse_utc("2022 13 04 00 52 30.0");
+        assert!(matches!(e, Err(ParsingError::MonthOutOfRange { value: 13 })));
+    }
+    #[test]
+    fn epoch_parse_rejects_invalid_day() {
+        let e = parse_utc("2022 02 30 00 52 30.0");
+        assert!(matches!(
+            e,
+            Err(ParsingError::DayOutOfRange {
+                value: 30,
+                max: 28,
+                year: 2022,
+                month: 2,
+            })
+        ));
+
+        // 2024 is a leap year: Feb 29th is valid
+        let e = parse_utc("2024 02 29 00 52 30.0");
+        assert!(e.is_ok());
+    }
+    #[test]
+    fn epoch_parse_rejects_invalid_hour() {
+        let e = parse_utc("2022 02 04 24 52 30.0");
+        assert!(matches!(e, Err(ParsingError::HourOutOfRange { value: 24 })));
+    }
+    #[test]
+    fn epoch_parse_rejects_invalid_minute() {
+        let e = parse_utc("2022 02 04 23 60 30.0");
+        assert!(matches!(
+            e,
+            Err(ParsingError::MinuteOutOfRange { value: 60 })
+        ));
+    }
+    #[test]
+    fn epoch_parse_allows_leap_second() {
+        // 2016-12-31 was an actual leap second insertion: hifitime accepts
+        // ss == 60 on this specific historical instant.
+        let e = parse_utc("2016 12 31 23 59 60.0");
+        assert!(e.is_ok());
+    }
+    #[test]
+    fn epoch_parse_rejects_out_of_range_second() {
+        let e = parse_utc("2022 02 04 23 59 61.0");
+        assert!(matches!(
+            e,
+            Err(ParsingError::SecondOutOfRange { value: 61 })
+        ));
+    }
+    #[test]
+    fn epoch_parse_rejects_leap_second_on_non_leap_date() {
+        // ss == 60 passes our own 0..=60 range guard, but 2022-02-04 was
+        // never an actual leap-second instant: hifitime's stricter
+        // validation must reject it without panicking.
+        let e = parse_utc("2022 02 04 23 59 60.0");
+        assert!(e.is_err());
+    }
 }
@@ -0,0 +1,168 @@
+//! ISO 8601 / RFC 3339 epoch parsing and emission, with explicit timescale
+//! tagging (an optional trailing token, e.g. `"... GPST"`, defaulting to
+//! the timescale passed in).
+
+use crate::epoch::{build_epoch, to_gregorian_in_timescale, ParsingError};
+use hifitime::{Epoch, TimeScale};
+
+/*
+ * Recognized trailing timescale tokens, e.g. the "GPST" in
+ * "2021-01-01T00:00:00Z GPST".
+ */
+fn timescale_token(tag: &str) -> Option<TimeScale> {
+    match tag {
+        "UTC" => Some(TimeScale::UTC),
+        "GPST" => Some(TimeScale::GPST),
+        "GST" => Some(TimeScale::GST),
+        "BDT" => Some(TimeScale::BDT),
+        _ => None,
+    }
+}
+
+/*
+ * Splits off up to `max` leading ASCII digits from `s`, char-boundary-safe
+ * (mirrors `format::take_digits`).
+ */
+fn take_digits(s: &str, max: usize) -> &str {
+    let mut end = 0;
+    for (count, (i, c)) in s.char_indices().enumerate() {
+        if count >= max || !c.is_ascii_digit() {
+            break;
+        }
+        end = i + c.len_utf8();
+    }
+    &s[..end]
+}
+
+/// Parses an RFC 3339 timestamp, e.g. `"2021-01-01T00:00:00.000000000Z"`
+/// or `"2021-01-01T00:00:00Z GPST"`, defaulting to `ts` when untagged.
+pub fn parse_rfc3339(s: &str, ts: TimeScale) -> Result<Epoch, ParsingError> {
+    let s = s.trim();
+
+    let (body, ts) = match s.rsplit_once(' ') {
+        Some((body, tag)) => match timescale_token(tag) {
+            Some(tagged) => (body, tagged),
+            None => (s, ts),
+        },
+        None => (s, ts),
+    };
+
+    let body = body.strip_suffix('Z').unwrap_or(body);
+    let (date, time) = body.split_once('T').ok_or(ParsingError::FormatError)?;
+
+    let mut date_fields = date.splitn(3, '-');
+    let y = date_fields
+        .next()
+        .ok_or(ParsingError::FormatError)?
+        .parse::<i32>()
+        .map_err(|_| ParsingError::YearField(date.to_string()))?;
+    let m = date_fields
+        .next()
+        .ok_or(ParsingError::FormatError)?
+        .parse::<u8>()
+        .map_err(|_| ParsingError::MonthField(date.to_string()))?;
+    let d = date_fields
+        .next()
+        .ok_or(ParsingError::FormatError)?
+        .parse::<u8>()
+        .map_err(|_| ParsingError::DayField(date.to_string()))?;
+
+    let (hms, fraction) = match time.split_once('.') {
+        Some((hms, fraction)) => (hms, Some(fraction)),
+        None => (time, None),
+    };
+
+    let mut time_fields = hms.splitn(3, ':');
+    let hh = time_fields
+        .next()
+        .ok_or(ParsingError::FormatError)?
+        .parse::<u8>()
+        .map_err(|_| ParsingError::HoursField(hms.to_string()))?;
+    let mm = time_fields
+        .next()
+        .ok_or(ParsingError::FormatError)?
+        .parse::<u8>()
+        .map_err(|_| ParsingError::MinutesField(hms.to_string()))?;
+    let ss = time_fields
+        .next()
+        .ok_or(ParsingError::FormatError)?
+        .parse::<u8>()
+        .map_err(|_| ParsingError::SecondsField(hms.to_string()))?;
+
+    let ns = match fraction {
+        Some(fraction) => {
+            // Take only a leading run of ASCII digits: slicing on a raw
+            // byte count is not char-boundary-safe and can panic on a
+            // garbled fractional field.
+            let digits = take_digits(fraction, 9);
+            if digits.is_empty() {
+                0
+            } else {
+                let scale = 10_u32.pow(9 - digits.len() as u32);
+                digits
+                    .parse::<u32>()
+                    .map_err(|_| ParsingError::NanosecondsField(digits.to_string()))?
+                    * scale
+            }
+        },
+        None => 0,
+    };
+
+    build_epoch(y, m, d, hh, mm, ss, ns, ts)
+}
+
+/// Formats `epoch` as an RFC 3339 timestamp in its own `TimeScale`. The
+/// timestamp always ends in `Z`; any timescale other than UTC then appends
+/// its tag after a space, e.g. `"2021-01-01T00:00:00.000000000Z GPST"`.
+pub fn to_rfc3339(epoch: Epoch) -> String {
+    let (y, m, d, hh, mm, ss, ns) = to_gregorian_in_timescale(epoch);
+    let timestamp = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+        y, m, d, hh, mm, ss, ns
+    );
+
+    if epoch.time_scale == TimeScale::UTC {
+        timestamp
+    } else {
+        format!("{} {}", timestamp, epoch.time_scale)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_utc_rfc3339() {
+        let e = parse_rfc3339("2021-01-01T00:00:00.000000000Z", TimeScale::UTC).unwrap();
+        assert_eq!(to_rfc3339(e), "2021-01-01T00:00:00.000000000Z");
+    }
+
+    #[test]
+    fn parse_defaults_to_passed_timescale() {
+        let e = parse_rfc3339("2021-01-01T00:00:00Z", TimeScale::GPST).unwrap();
+        assert_eq!(e.time_scale, TimeScale::GPST);
+    }
+
+    #[test]
+    fn parse_honors_explicit_trailing_timescale() {
+        let e = parse_rfc3339("2021-01-01T00:00:00Z GPST", TimeScale::UTC).unwrap();
+        assert_eq!(e.time_scale, TimeScale::GPST);
+        assert_eq!(to_rfc3339(e), "2021-01-01T00:00:00.000000000Z GPST");
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(parse_rfc3339("not-a-timestamp", TimeScale::UTC).is_err());
+    }
+
+    #[test]
+    fn handles_non_ascii_fraction_without_panicking() {
+        // a garbled fractional-seconds field, e.g. from a corrupt JSON
+        // sidecar: must not panic on a byte slice that lands mid-codepoint.
+        // The leading run of ASCII digits before the garbage byte is used,
+        // the rest is ignored.
+        let e = parse_rfc3339("2021-01-01T00:00:00.12345678\u{fc}9Z", TimeScale::UTC);
+        assert!(e.is_ok());
+    }
+}